@@ -0,0 +1,65 @@
+//! Typed errors shared across the derivation pipeline's stages.
+
+use alloc::string::String;
+use core::fmt;
+
+/// Errors produced while advancing a pipeline stage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StageError {
+    /// The current L1 origin has no more data to retrieve. Not a failure: the caller should
+    /// advance to the next L1 origin and retry, rather than treat this as a fatal error.
+    Eof,
+    /// The provider could not satisfy the request right now but may on a subsequent attempt,
+    /// e.g. a transient RPC hiccup against a blob/alt-DA backend.
+    NotEnoughData,
+    /// A fatal, non-retryable error.
+    Critical(String),
+}
+
+impl StageError {
+    /// Returns `true` if retrying the operation that produced this error may succeed.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::NotEnoughData)
+    }
+}
+
+impl fmt::Display for StageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Eof => write!(f, "no more data in the current L1 origin"),
+            Self::NotEnoughData => write!(f, "provider could not satisfy the request (transient)"),
+            Self::Critical(msg) => write!(f, "critical stage error: {msg}"),
+        }
+    }
+}
+
+impl core::error::Error for StageError {}
+
+/// Classifies an `anyhow::Error` surfaced by a provider (a [crate::traits::ChainProvider] or
+/// [crate::traits::DataAvailabilityProvider]) as transient or fatal. Providers that want
+/// finer-grained retry behavior can embed a [StageError] in the error they return; anything else
+/// is treated as [StageError::Critical], since an arbitrary provider error carries no guarantee
+/// it's safe to retry.
+pub(crate) fn classify_provider_error(err: anyhow::Error) -> StageError {
+    match err.downcast::<StageError>() {
+        Ok(stage_err) => stage_err,
+        Err(err) => StageError::Critical(alloc::format!("{err}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_provider_error_unwraps_an_embedded_stage_error() {
+        let err = anyhow::Error::from(StageError::NotEnoughData);
+        assert_eq!(classify_provider_error(err), StageError::NotEnoughData);
+    }
+
+    #[test]
+    fn classify_provider_error_treats_unrecognized_errors_as_critical() {
+        let err = anyhow::anyhow!("connection reset by peer");
+        assert!(matches!(classify_provider_error(err), StageError::Critical(_)));
+    }
+}
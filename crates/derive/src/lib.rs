@@ -0,0 +1,15 @@
+//! `kona-derive`
+//!
+//! The L2 derivation pipeline: consumes L1 data and produces the inputs needed to build L2
+//! payload attributes. Built `no_std` so it can run inside the fault proof program.
+
+#![no_std]
+
+extern crate alloc;
+#[cfg(test)]
+extern crate std;
+
+pub mod errors;
+pub mod stages;
+pub mod traits;
+pub mod types;
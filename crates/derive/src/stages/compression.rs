@@ -0,0 +1,100 @@
+//! Transparent decoding of the one-byte compression-scheme header some alt-DA backends prefix
+//! to each retrieved frame.
+
+use alloc::{string::String, vec::Vec};
+use alloy_primitives::Bytes;
+use ruzstd::io::Read as _;
+
+/// The one-byte header tag prefixed to a DA frame, indicating how the remainder is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CompressionScheme {
+    /// The payload follows the header byte untouched.
+    Plain = 0x00,
+    /// The payload is Zstd-compressed and must be inflated before use.
+    Zstd = 0x01,
+}
+
+impl CompressionScheme {
+    /// Resolves a header byte to a [CompressionScheme], or `None` if it's not recognized.
+    fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0x00 => Some(Self::Plain),
+            0x01 => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// Errors returned while decoding a tagged DA frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The frame was empty; there was no header byte to read.
+    EmptyFrame,
+    /// The header byte did not match a known [CompressionScheme].
+    UnknownScheme(u8),
+    /// The frame was tagged [CompressionScheme::Zstd] but failed to inflate.
+    ZstdDecompression(String),
+}
+
+/// Strips the leading [CompressionScheme] header from `frame` and, if it's tagged
+/// [CompressionScheme::Zstd], transparently decompresses the remainder. [CompressionScheme::Plain]
+/// frames are passed through untouched.
+pub fn decode_frame(frame: &[u8]) -> Result<Bytes, DecodeError> {
+    let (tag, payload) = frame.split_first().ok_or(DecodeError::EmptyFrame)?;
+    let scheme = CompressionScheme::from_tag(*tag).ok_or(DecodeError::UnknownScheme(*tag))?;
+    match scheme {
+        CompressionScheme::Plain => Ok(Bytes::copy_from_slice(payload)),
+        CompressionScheme::Zstd => {
+            // `ruzstd::decode_all` is written against `std::io::Read` and isn't available
+            // outside `std`; the streaming decoder operates directly on a `&[u8]` via
+            // `ruzstd`'s own no_std `Read` trait, so it works inside the fault proof program.
+            let mut decoder = ruzstd::StreamingDecoder::new(payload)
+                .map_err(|e| DecodeError::ZstdDecompression(alloc::format!("{e}")))?;
+            let mut decoded = Vec::new();
+            decoder
+                .read_to_end(&mut decoded)
+                .map_err(|e| DecodeError::ZstdDecompression(alloc::format!("{e}")))?;
+            Ok(Bytes::from(decoded))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn decodes_plain_frame() {
+        let mut frame = vec![CompressionScheme::Plain as u8];
+        frame.extend_from_slice(b"hello world");
+
+        let decoded = decode_frame(&frame).expect("plain frame should decode");
+        assert_eq!(decoded.as_ref(), b"hello world");
+    }
+
+    #[test]
+    fn decodes_zstd_frame() {
+        let compressed = zstd::stream::encode_all(&b"hello world"[..], 0)
+            .expect("failed to compress test fixture");
+        let mut frame = vec![CompressionScheme::Zstd as u8];
+        frame.extend_from_slice(&compressed);
+
+        let decoded = decode_frame(&frame).expect("zstd frame should decode");
+        assert_eq!(decoded.as_ref(), b"hello world");
+    }
+
+    #[test]
+    fn rejects_corrupt_header() {
+        let frame = vec![0xFFu8, 1, 2, 3];
+        let err = decode_frame(&frame).expect_err("unknown scheme tag should error");
+        assert_eq!(err, DecodeError::UnknownScheme(0xFF));
+    }
+
+    #[test]
+    fn rejects_empty_frame() {
+        let err = decode_frame(&[]).expect_err("empty frame should error");
+        assert_eq!(err, DecodeError::EmptyFrame);
+    }
+}
@@ -0,0 +1,253 @@
+//! Contains the [L1Traversal] stage of the derivation pipeline.
+
+use crate::{
+    errors::{classify_provider_error, StageError},
+    traits::ChainProvider,
+    types::{BlockInfo, SystemConfig},
+};
+use alloc::collections::VecDeque;
+use anyhow::{anyhow, Result};
+
+/// Maximum number of advanced-past L1 origins retained for reorg detection. A reorg deeper than
+/// this many blocks can't be localized - [L1Traversal::local_block] simply won't find a record that far
+/// back - which is an acceptable trade-off for bounding this stage's memory use for the lifetime
+/// of the pipeline, running as it does inside a resource-constrained fault proof program.
+const MAX_ADVANCED_HISTORY: usize = 256;
+
+/// The L1 traversal stage of the derivation pipeline.
+///
+/// Walks the L1 chain one block at a time, handing each block off to [super::L1Retrieval] as the
+/// origin from which to pull batcher data.
+#[derive(Debug)]
+pub struct L1Traversal<CP: ChainProvider> {
+    /// The current L1 origin, if the pipeline has not yet consumed it.
+    block: Option<BlockInfo>,
+    /// The chain provider used to fetch L1 block data.
+    pub chain_provider: CP,
+    /// The system config at the current L1 origin.
+    pub system_config: SystemConfig,
+    /// The most recent [MAX_ADVANCED_HISTORY] L1 origins the pipeline has advanced past, in
+    /// ascending order by block number. Used to detect and localize L1 reorgs; trimmed back to
+    /// the common ancestor on reset.
+    advanced: VecDeque<BlockInfo>,
+}
+
+impl<CP: ChainProvider> L1Traversal<CP> {
+    /// Creates a new [L1Traversal] starting from `block`.
+    pub fn new(chain_provider: CP, system_config: SystemConfig, block: BlockInfo) -> Self {
+        Self {
+            block: Some(block),
+            chain_provider,
+            system_config,
+            advanced: VecDeque::new(),
+        }
+    }
+
+    /// Returns the current L1 origin, if it hasn't yet been consumed by [Self::next_l1_block].
+    pub fn origin(&self) -> Option<&BlockInfo> {
+        self.block.as_ref()
+    }
+
+    /// Takes the current L1 origin, leaving `None` in its place, and records it as advanced-past
+    /// for future reorg detection, evicting the oldest record once [MAX_ADVANCED_HISTORY] is
+    /// exceeded.
+    pub fn next_l1_block(&mut self) -> Option<BlockInfo> {
+        let block = self.block.take()?;
+        self.advanced.push_back(block);
+        if self.advanced.len() > MAX_ADVANCED_HISTORY {
+            self.advanced.pop_front();
+        }
+        Some(block)
+    }
+
+    /// Resets traversal to `ancestor`, discarding any advanced-past history beyond it. The next
+    /// call to [Self::next_l1_block] will re-hand out `ancestor` itself.
+    pub fn reset_to(&mut self, ancestor: BlockInfo) {
+        self.advanced.retain(|b| b.number <= ancestor.number);
+        self.block = Some(ancestor);
+    }
+
+    /// Advances traversal to the L1 block following the one most recently handed out by
+    /// [Self::next_l1_block], fetching it from the [ChainProvider] and queuing it so the next
+    /// call to [Self::next_l1_block] returns it. A no-op if a block is already queued (i.e. the
+    /// previous one hasn't been consumed yet) or if nothing has been consumed yet - returns
+    /// `Ok(None)` in the latter case, since there's no current block to advance from.
+    ///
+    /// Returns [StageError::NotEnoughData] rather than [StageError::Critical] when the
+    /// [ChainProvider] reports the next block isn't available yet (see the transient/fatal
+    /// convention documented on [ChainProvider]) - the ordinary case of having caught up to the
+    /// L1 chain tip, which the caller should retry rather than treat as fatal.
+    pub async fn advance_l1_block(&mut self) -> Result<Option<BlockInfo>, StageError> {
+        if self.block.is_some() {
+            return Ok(self.block);
+        }
+        let Some(current) = self.advanced.back().copied() else {
+            return Ok(None);
+        };
+
+        let next = self
+            .chain_provider
+            .block_info_by_number(current.number + 1)
+            .await
+            .map_err(classify_provider_error)?;
+        self.block = Some(next);
+        Ok(Some(next))
+    }
+
+    /// Checks whether `candidate`, the next L1 origin about to be retrieved, actually extends the
+    /// chain the pipeline derived `last_known` from.
+    ///
+    /// Returns `Ok(None)` when `candidate.parent_hash == last_known.hash` (no reorg). Otherwise
+    /// the local view of L1 has diverged from the provider's, and this binary-searches
+    /// `[last_known.number, candidate.number]` over the [ChainProvider] for the first L1 block
+    /// whose provider-returned hash no longer matches the locally advanced chain, returning the
+    /// block just before that divergence point as the new common ancestor.
+    pub async fn detect_reorg(
+        &mut self,
+        last_known: &BlockInfo,
+        candidate: &BlockInfo,
+    ) -> Result<Option<BlockInfo>> {
+        if candidate.parent_hash == last_known.hash {
+            return Ok(None);
+        }
+
+        // Invariant: `lo` agrees with the provider, `hi` diverges from it.
+        let mut lo = last_known.number;
+        let hi_bound = candidate.number;
+        let mut hi = hi_bound;
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+            let local = self.local_block(mid)?;
+            let remote = self.chain_provider.block_info_by_number(mid).await?;
+            if remote.hash == local.hash {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+
+        self.local_block(lo)
+    }
+
+    /// Looks up the locally-advanced record for L1 block `number`.
+    fn local_block(&self, number: u64) -> Result<BlockInfo> {
+        self.advanced
+            .iter()
+            .find(|b| b.number == number)
+            .copied()
+            .ok_or_else(|| anyhow!("no local record of L1 block {number} to compare against"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{stages::test_support::{block, FakeChainProvider}, types::SystemConfig};
+
+    /// A consistent chain of `n` blocks, where block `i`'s hash is `repeat_byte(i)` and its
+    /// parent hash is block `i - 1`'s hash.
+    fn chain(n: u64) -> alloc::vec::Vec<BlockInfo> {
+        (0..n)
+            .map(|i| block(i, i as u8, i.wrapping_sub(1) as u8))
+            .collect()
+    }
+
+    /// Feeds `blocks` through `next_l1_block` one at a time so they end up in `advanced`,
+    /// without requiring a real advance through the chain provider.
+    fn seed_history(provider: FakeChainProvider, blocks: &[BlockInfo]) -> L1Traversal<FakeChainProvider> {
+        let mut traversal = L1Traversal::new(provider, SystemConfig::default(), blocks[0]);
+        for &b in blocks {
+            traversal.reset_to(b);
+            traversal.next_l1_block();
+        }
+        traversal
+    }
+
+    #[test]
+    fn no_reorg_when_parent_hash_matches() {
+        let local = chain(3);
+        let mut traversal = seed_history(FakeChainProvider::default(), &local);
+
+        let last_known = local[2];
+        let candidate = block(3, 3, 2); // parent_hash == local[2].hash
+
+        let result = futures::executor::block_on(traversal.detect_reorg(&last_known, &candidate))
+            .expect("detect_reorg should succeed");
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn bisects_to_find_common_ancestor() {
+        let local = chain(7); // blocks 0..=6
+
+        let mut remote = FakeChainProvider::default();
+        for i in 0..=3u64 {
+            remote.blocks.insert(i, local[i as usize]); // agrees with local
+        }
+        for i in 4..=6u64 {
+            // Diverged: same number, different hash/parent-hash than the local record.
+            remote.blocks.insert(i, block(i, 100 + i as u8, 100 + i as u8 - 1));
+        }
+
+        let mut traversal = seed_history(remote, &local);
+
+        // Simulate a pipeline that hasn't reconfirmed since block 0.
+        let last_known = local[0];
+        let candidate = block(7, 107, 106); // parent_hash diverges from local[6].hash
+
+        let ancestor = futures::executor::block_on(traversal.detect_reorg(&last_known, &candidate))
+            .expect("detect_reorg should succeed")
+            .expect("a reorg should be detected");
+
+        assert_eq!(ancestor, local[3]);
+    }
+
+    #[test]
+    fn advance_l1_block_walks_forward_via_chain_provider() {
+        let b0 = block(0, 0, 0);
+        let b1 = block(1, 1, 0);
+
+        let mut provider = FakeChainProvider::default();
+        provider.blocks.insert(1, b1);
+
+        let mut traversal = L1Traversal::new(provider, SystemConfig::default(), b0);
+
+        // Nothing to advance from until the current origin is consumed.
+        assert_eq!(
+            futures::executor::block_on(traversal.advance_l1_block()).unwrap(),
+            Some(b0)
+        );
+
+        assert_eq!(traversal.next_l1_block(), Some(b0));
+        assert_eq!(traversal.origin(), None);
+
+        let advanced = futures::executor::block_on(traversal.advance_l1_block())
+            .expect("advance_l1_block should succeed");
+        assert_eq!(advanced, Some(b1));
+        assert_eq!(traversal.origin(), Some(&b1));
+    }
+
+    #[test]
+    fn advance_l1_block_surfaces_chain_tip_as_not_enough_data() {
+        // No block queued for number 1: `FakeChainProvider` embeds `StageError::NotEnoughData`
+        // for unknown blocks, as a real `ChainProvider` would when the pipeline has caught up to
+        // the L1 chain tip.
+        let b0 = block(0, 0, 0);
+        let mut traversal = L1Traversal::new(FakeChainProvider::default(), SystemConfig::default(), b0);
+        traversal.next_l1_block();
+
+        let err = futures::executor::block_on(traversal.advance_l1_block())
+            .expect_err("catching up to the tip should not be fatal");
+        assert_eq!(err, StageError::NotEnoughData, "should be classified transient, not Critical");
+    }
+
+    #[test]
+    fn advanced_history_is_bounded() {
+        let mut traversal = L1Traversal::new(FakeChainProvider::default(), SystemConfig::default(), block(0, 0, 0));
+        for i in 1..=(MAX_ADVANCED_HISTORY as u64 + 10) {
+            traversal.reset_to(block(i, i as u8, i.wrapping_sub(1) as u8));
+            traversal.next_l1_block();
+        }
+        assert_eq!(traversal.advanced.len(), MAX_ADVANCED_HISTORY);
+    }
+}
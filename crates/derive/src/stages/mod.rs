@@ -0,0 +1,11 @@
+//! The stages of the L2 derivation pipeline.
+
+mod compression;
+mod l1_retrieval;
+mod l1_traversal;
+#[cfg(test)]
+mod test_support;
+
+pub use compression::{decode_frame, CompressionScheme, DecodeError};
+pub use l1_retrieval::{L1Retrieval, StepOutcome};
+pub use l1_traversal::L1Traversal;
@@ -0,0 +1,32 @@
+//! Fixtures shared by this module's stages' test suites.
+
+use crate::{errors::StageError, traits::ChainProvider, types::BlockInfo};
+use alloy_primitives::B256;
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+
+/// Builds a `BlockInfo` whose hash and parent hash are derived from single repeated bytes, so
+/// tests can construct consistent (or deliberately diverging) chains by hand.
+pub(crate) fn block(number: u64, hash_byte: u8, parent_hash_byte: u8) -> BlockInfo {
+    BlockInfo {
+        number,
+        hash: B256::repeat_byte(hash_byte),
+        parent_hash: B256::repeat_byte(parent_hash_byte),
+        timestamp: number,
+    }
+}
+
+/// A [ChainProvider] backed by a fixed map of block number to [BlockInfo], seeded by hand.
+#[derive(Debug, Default)]
+pub(crate) struct FakeChainProvider {
+    pub(crate) blocks: BTreeMap<u64, BlockInfo>,
+}
+
+#[async_trait]
+impl ChainProvider for FakeChainProvider {
+    async fn block_info_by_number(&mut self, number: u64) -> anyhow::Result<BlockInfo> {
+        // Mirrors the convention documented on `ChainProvider`: an unseeded block looks like the
+        // chain tip not having produced it yet, not a hard provider failure.
+        self.blocks.get(&number).copied().ok_or_else(|| anyhow::Error::from(StageError::NotEnoughData))
+    }
+}
@@ -0,0 +1,69 @@
+//! Core traits implemented by the derivation pipeline stages and their external dependencies.
+
+use crate::{
+    errors::StageError,
+    types::{BlockInfo, SystemConfig},
+};
+use alloc::boxed::Box;
+use alloy_primitives::{Address, Bytes};
+use anyhow::Result;
+use async_trait::async_trait;
+use core::time::Duration;
+
+/// Supplies L1 chain data to the derivation pipeline.
+///
+/// An L1 block that hasn't been produced yet (the traversal stage has caught up to the chain
+/// tip) is an ordinary, expected condition, not a failure: signal it by returning
+/// `Err(anyhow::Error::from(StageError::NotEnoughData))` rather than an arbitrary error, so
+/// callers can tell it apart from a genuine provider failure and retry instead of aborting.
+#[async_trait]
+pub trait ChainProvider {
+    /// Returns the [BlockInfo] for the L1 block with the given number.
+    async fn block_info_by_number(&mut self, number: u64) -> Result<BlockInfo>;
+}
+
+/// An iterator over data items retrieved from a [DataAvailabilityProvider].
+pub trait DataIter<T> {
+    /// Returns the next data item, or `None` if the iterator is exhausted.
+    fn next(&mut self) -> Option<T>;
+}
+
+/// Supplies the raw batcher data posted to L1 (or an alt-DA backend) for a given L1 block.
+///
+/// A transient hiccup (e.g. a blob/alt-DA backend that isn't available yet) is an ordinary,
+/// expected condition, not a failure: signal it by returning
+/// `Err(anyhow::Error::from(StageError::NotEnoughData))` rather than an arbitrary error, so
+/// callers can retry it instead of treating it as fatal (see
+/// [crate::stages::L1Retrieval]'s retry wrapper around [Self::open_data]).
+#[async_trait]
+pub trait DataAvailabilityProvider {
+    /// The iterator type returned by [Self::open_data].
+    type DataIter<T>: DataIter<T>
+    where
+        T: Into<Bytes>;
+
+    /// Opens the data posted by `batcher_address` in the given L1 block.
+    async fn open_data<T: Into<Bytes>>(
+        &self,
+        block_ref: &BlockInfo,
+        batcher_address: Address,
+    ) -> Result<Self::DataIter<T>>;
+}
+
+/// A stage of the pipeline that can be reset to a new L1 origin, e.g. following a reorg.
+#[async_trait]
+pub trait ResettableStage {
+    /// Resets the stage to the given L1 `base` block and [SystemConfig].
+    async fn reset(&mut self, base: BlockInfo, cfg: SystemConfig) -> Result<(), StageError>;
+}
+
+/// Performs the delay between retried provider calls during capped exponential backoff.
+///
+/// Injected by the caller rather than called directly (e.g. via `futures-timer`) so this crate
+/// can stay `no_std` and run inside the fault proof program: a `std` host can wire up a real
+/// timer, while the fault proof program supplies whatever notion of time it has available.
+#[async_trait]
+pub trait Sleeper: core::fmt::Debug {
+    /// Sleeps for approximately `duration`.
+    async fn sleep(&self, duration: Duration);
+}
@@ -0,0 +1,23 @@
+//! Types shared across the derivation pipeline.
+
+use alloy_primitives::{Address, B256};
+
+/// Identifies a single L1 or L2 block by number, hash, parent hash, and timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BlockInfo {
+    /// The block number.
+    pub number: u64,
+    /// The block hash.
+    pub hash: B256,
+    /// The parent block hash.
+    pub parent_hash: B256,
+    /// The block timestamp.
+    pub timestamp: u64,
+}
+
+/// The rollup system configuration, derived from L1 deposit events and config-update log events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SystemConfig {
+    /// The batcher address authorized to submit batch data to L1.
+    pub batcher_addr: Address,
+}